@@ -0,0 +1,290 @@
+//! # Debt Treasury Module
+//!
+//! ## Overview
+//!
+//! Holds collateral and stablecoin debt seized from unsafe positions on behalf of the lend
+//! module. `debit_pool` tracks stablecoin value owed by the system that hasn't yet been covered
+//! by a sale of the matching collateral; `surplus_pool` tracks stablecoin accrued by the system
+//! (e.g. from stability fee interest) that hasn't yet been put to use.
+//!
+//! Collateral confiscated in large enough lots is sold off through a descending-price (Dutch)
+//! collateral auction rather than sitting idle in the pool: see `CollateralAuctions`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, traits::Get};
+use frame_system::ensure_signed;
+use orml_traits::MultiCurrency;
+use primitives::{Balance, CurrencyId};
+use sp_runtime::{
+	traits::{AccountIdConversion, Saturating, UniqueSaturatedInto, Zero},
+	DispatchResult, FixedPointNumber, ModuleId, RuntimeDebug,
+};
+use support::{DEBTTreasury, PriceProvider, Ratio};
+
+mod mock;
+mod tests;
+
+pub type AuctionId = u32;
+
+/// A single descending-price sale of confiscated collateral, started to recover `target`
+/// stablecoin on behalf of the position that was liquidated.
+#[derive(Encode, Decode, Clone, RuntimeDebug, PartialEq, Eq)]
+pub struct CollateralAuction<AccountId, BlockNumber> {
+	pub currency_id: CurrencyId,
+	/// Collateral still up for sale.
+	pub amount: Balance,
+	/// Stablecoin still owed that this auction is trying to raise.
+	pub target: Balance,
+	pub start_block: BlockNumber,
+	/// Price, in stablecoin per unit collateral, at `start_block`.
+	pub start_price: Ratio,
+	/// Owner of the liquidated position; receives any collateral left over once `target` is
+	/// fully covered.
+	pub refund_recipient: AccountId,
+}
+
+pub trait Trait: frame_system::Trait {
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+	type Currency: MultiCurrency<Self::AccountId, CurrencyId = CurrencyId, Balance = Balance>;
+	type StableCurrencyId: Get<CurrencyId>;
+	type PriceSource: PriceProvider<CurrencyId, Ratio>;
+	/// Confiscated lots below this size are pooled instead of auctioned.
+	type MinimumCollateralAuctionSize: Get<Balance>;
+	/// Premium over the oracle price an auction starts at.
+	type AuctionStartPremium: Get<Ratio>;
+	/// Fraction of the start price an auction's price decays down to.
+	type AuctionMinimumPriceRatio: Get<Ratio>;
+	/// Blocks over which an auction's price decays from its start price to its floor.
+	type AuctionDuration: Get<Self::BlockNumber>;
+	type ModuleId: Get<ModuleId>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as DEBTTreasury {
+		/// Stablecoin value the system owes but hasn't yet raised by selling collateral.
+		pub DebitPool get(fn debit_pool): Balance;
+
+		/// Stablecoin value the system has accrued (e.g. stability fee interest) and hasn't yet
+		/// put to use.
+		pub SurplusPool get(fn surplus_pool): Balance;
+
+		/// Collateral of each currency pooled by the treasury (too small to be worth auctioning),
+		/// pending reclaim.
+		pub TotalCollaterals get(fn get_total_collaterals): map hasher(twox_64_concat) CurrencyId => Balance;
+
+		/// Active collateral auctions.
+		pub CollateralAuctions get(fn collateral_auctions): map hasher(twox_64_concat) AuctionId => Option<CollateralAuction<T::AccountId, T::BlockNumber>>;
+		pub NextCollateralAuctionId get(fn next_collateral_auction_id): AuctionId;
+	}
+}
+
+decl_event!(
+	pub enum Event<T>
+	where
+		<T as frame_system::Trait>::AccountId,
+	{
+		/// Collateral was pooled directly into the treasury (below the auction threshold).
+		CollateralDeposited(CurrencyId, Balance),
+		/// A collateral auction was started to recover `target` stablecoin.
+		CollateralAuctionStarted(AuctionId, CurrencyId, Balance, Balance),
+		/// A bid was accepted: bidder paid `Balance` stablecoin for `Balance` collateral.
+		CollateralAuctionBid(AuctionId, AccountId, Balance, Balance),
+		/// An auction closed, either because its target was fully covered (any leftover collateral
+		/// was refunded) or because its lot ran out.
+		CollateralAuctionSettled(AuctionId),
+	}
+);
+
+decl_error! {
+	pub enum Error for Module<T: Trait> {
+		/// The treasury does not hold enough of the requested collateral.
+		CollateralNotEnough,
+		/// No active auction with this id.
+		AuctionNotFound,
+		/// A bid must be strictly positive.
+		InvalidBidAmount,
+		/// The auction's current price could not be evaluated.
+		InvalidAuctionPrice,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		/// Bid `payment` stablecoin on auction `auction_id`, claiming the collateral it buys at
+		/// the auction's current price. `payment` is capped to the auction's remaining target, so
+		/// a bidder may cover only part of the lot.
+		#[weight = 10_000]
+		pub fn bid_collateral_auction(origin, auction_id: AuctionId, payment: Balance) {
+			let bidder = ensure_signed(origin)?;
+			Self::bid(&bidder, auction_id, payment)?;
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	pub fn account_id() -> T::AccountId {
+		T::ModuleId::get().into_account()
+	}
+
+	/// Linearly decay `auction`'s price from `start_price` at `start_block` down to its floor
+	/// over `AuctionDuration` blocks.
+	pub fn collateral_auction_current_price(auction: &CollateralAuction<T::AccountId, T::BlockNumber>) -> Ratio {
+		let floor_price = auction.start_price.saturating_mul(T::AuctionMinimumPriceRatio::get());
+		let duration: Balance = T::AuctionDuration::get().unique_saturated_into();
+		if duration.is_zero() {
+			return floor_price;
+		}
+
+		let elapsed: Balance = frame_system::Module::<T>::block_number()
+			.saturating_sub(auction.start_block)
+			.unique_saturated_into();
+		let decayed = Ratio::saturating_from_rational(elapsed.min(duration), duration);
+		let price_drop = auction.start_price.saturating_sub(floor_price).saturating_mul(decayed);
+		auction.start_price.saturating_sub(price_drop).max(floor_price)
+	}
+
+	fn bid(bidder: &T::AccountId, auction_id: AuctionId, payment: Balance) -> DispatchResult {
+		let mut auction = Self::collateral_auctions(auction_id).ok_or(Error::<T>::AuctionNotFound)?;
+		let payment = payment.min(auction.target);
+		ensure!(!payment.is_zero(), Error::<T>::InvalidBidAmount);
+
+		let current_price = Self::collateral_auction_current_price(&auction);
+		ensure!(!current_price.is_zero(), Error::<T>::InvalidAuctionPrice);
+
+		// Collateral is handed over strictly in proportion to what's actually paid, capped by
+		// what's left in the lot; handing over anything more (e.g. the full gap down to the
+		// post-payment target) would let a bidder walk off with most of the lot for a token
+		// payment.
+		let collateral_for_bidder =
+			sp_runtime::helpers_128bit::multiply_by_rational(payment, Ratio::accuracy(), current_price.into_inner())
+				.unwrap_or_else(|_| Balance::max_value())
+				.min(auction.amount);
+
+		T::Currency::withdraw(T::StableCurrencyId::get(), bidder, payment)?;
+		T::Currency::transfer(auction.currency_id, &Self::account_id(), bidder, collateral_for_bidder)?;
+		DebitPool::mutate(|pool| *pool = pool.saturating_sub(payment));
+
+		auction.amount = auction.amount.saturating_sub(collateral_for_bidder);
+		auction.target = auction.target.saturating_sub(payment);
+		Self::deposit_event(Event::<T>::CollateralAuctionBid(
+			auction_id,
+			bidder.clone(),
+			payment,
+			collateral_for_bidder,
+		));
+
+		// Settle once the target is fully covered, or once the lot is exhausted. The latter can
+		// happen when the confiscated lot is worth less than `target` (e.g. `target` includes a
+		// liquidation penalty on top of the bad debt) -- without it, a now-empty auction would
+		// stay open forever instead of closing with the shortfall left in `DebitPool`.
+		if auction.target.is_zero() || auction.amount.is_zero() {
+			if !auction.amount.is_zero() {
+				T::Currency::transfer(auction.currency_id, &Self::account_id(), &auction.refund_recipient, auction.amount)?;
+			}
+			CollateralAuctions::<T>::remove(auction_id);
+			Self::deposit_event(Event::<T>::CollateralAuctionSettled(auction_id));
+		} else {
+			CollateralAuctions::<T>::insert(auction_id, auction);
+		}
+
+		Ok(())
+	}
+
+	fn new_collateral_auction(refund_recipient: T::AccountId, currency_id: CurrencyId, amount: Balance, target: Balance) {
+		let start_price = T::PriceSource::get_price(currency_id)
+			.unwrap_or_else(Ratio::zero)
+			.saturating_mul(Ratio::one().saturating_add(T::AuctionStartPremium::get()));
+
+		let auction_id = Self::next_collateral_auction_id();
+		NextCollateralAuctionId::put(auction_id.wrapping_add(1));
+		CollateralAuctions::<T>::insert(
+			auction_id,
+			CollateralAuction {
+				currency_id,
+				amount,
+				target,
+				start_block: frame_system::Module::<T>::block_number(),
+				start_price,
+				refund_recipient,
+			},
+		);
+
+		Self::deposit_event(Event::<T>::CollateralAuctionStarted(auction_id, currency_id, amount, target));
+	}
+}
+
+impl<T: Trait> DEBTTreasury<T::AccountId> for Module<T> {
+	type Balance = Balance;
+	type CurrencyId = CurrencyId;
+
+	fn get_debit_pool() -> Self::Balance {
+		Self::debit_pool()
+	}
+
+	fn get_surplus_pool() -> Self::Balance {
+		Self::surplus_pool()
+	}
+
+	fn get_total_collaterals(currency_id: Self::CurrencyId) -> Self::Balance {
+		Self::get_total_collaterals(currency_id)
+	}
+
+	fn deposit_collateral(from: &T::AccountId, currency_id: Self::CurrencyId, amount: Self::Balance) -> DispatchResult {
+		T::Currency::transfer(currency_id, from, &Self::account_id(), amount)?;
+		TotalCollaterals::mutate(currency_id, |total| *total = total.saturating_add(amount));
+		Self::deposit_event(Event::<T>::CollateralDeposited(currency_id, amount));
+		Ok(())
+	}
+
+	fn withdraw_collateral(to: &T::AccountId, currency_id: Self::CurrencyId, amount: Self::Balance) -> DispatchResult {
+		ensure_collateral_available::<T>(currency_id, amount)?;
+		TotalCollaterals::mutate(currency_id, |total| *total = total.saturating_sub(amount));
+		T::Currency::transfer(currency_id, &Self::account_id(), to, amount)?;
+		Ok(())
+	}
+
+	fn on_system_debit(amount: Self::Balance) {
+		DebitPool::mutate(|pool| *pool = pool.saturating_add(amount));
+	}
+
+	fn on_system_surplus(amount: Self::Balance) {
+		SurplusPool::mutate(|pool| *pool = pool.saturating_add(amount));
+	}
+
+	/// Take custody of confiscated collateral on behalf of a liquidated position. Lots at or
+	/// above `MinimumCollateralAuctionSize` are sold through a Dutch auction instead of being
+	/// pooled; `target` is the stablecoin value the auction should try to recover, with any
+	/// leftover collateral refunded to `refund_recipient` once it does.
+	fn create_collateral_auction(
+		from: &T::AccountId,
+		refund_recipient: &T::AccountId,
+		currency_id: Self::CurrencyId,
+		amount: Self::Balance,
+		target: Self::Balance,
+	) -> DispatchResult {
+		T::Currency::transfer(currency_id, from, &Self::account_id(), amount)?;
+
+		if amount < T::MinimumCollateralAuctionSize::get() {
+			TotalCollaterals::mutate(currency_id, |total| *total = total.saturating_add(amount));
+			Self::deposit_event(Event::<T>::CollateralDeposited(currency_id, amount));
+		} else {
+			Self::new_collateral_auction(refund_recipient.clone(), currency_id, amount, target);
+		}
+
+		Ok(())
+	}
+}
+
+fn ensure_collateral_available<T: Trait>(currency_id: CurrencyId, amount: Balance) -> DispatchResult {
+	ensure!(
+		Module::<T>::get_total_collaterals(currency_id) >= amount,
+		Error::<T>::CollateralNotEnough
+	);
+	Ok(())
+}
@@ -0,0 +1,133 @@
+//! Mocks for the debt treasury module.
+
+#![cfg(test)]
+
+use super::*;
+use frame_support::{impl_outer_event, impl_outer_origin, parameter_types};
+use primitives::TokenSymbol;
+use sp_core::H256;
+use sp_runtime::{testing::Header, traits::IdentityLookup, Perbill};
+use support::PriceProvider;
+
+pub type AccountId = u128;
+pub type BlockNumber = u64;
+
+pub const ALICE: AccountId = 1;
+pub const BTC: CurrencyId = CurrencyId::Token(TokenSymbol::XBTC);
+pub const XUSD: CurrencyId = CurrencyId::Token(TokenSymbol::XUSD);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Runtime;
+
+impl_outer_origin! {
+	pub enum Origin for Runtime {}
+}
+
+impl_outer_event! {
+	pub enum TestEvent for Runtime {
+		frame_system<T>,
+		orml_tokens<T>,
+		debt_treasury<T>,
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const MaximumBlockWeight: u32 = 1024;
+	pub const MaximumBlockLength: u32 = 2 * 1024;
+	pub const AvailableBlockRatio: Perbill = Perbill::one();
+}
+
+impl frame_system::Trait for Runtime {
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Call = ();
+	type Hash = H256;
+	type Hashing = ::sp_runtime::traits::BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = TestEvent;
+	type BlockHashCount = BlockHashCount;
+	type MaximumBlockWeight = MaximumBlockWeight;
+	type MaximumBlockLength = MaximumBlockLength;
+	type AvailableBlockRatio = AvailableBlockRatio;
+	type Version = ();
+	type PalletInfo = ();
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type DbWeight = ();
+	type BlockExecutionWeight = ();
+	type ExtrinsicBaseWeight = ();
+	type MaximumExtrinsicWeight = ();
+	type BaseCallFilter = ();
+	type SystemWeightInfo = ();
+}
+pub type System = frame_system::Module<Runtime>;
+
+impl orml_tokens::Trait for Runtime {
+	type Event = TestEvent;
+	type Balance = Balance;
+	type Amount = primitives::Amount;
+	type CurrencyId = CurrencyId;
+	type OnReceived = ();
+	type WeightInfo = ();
+}
+pub type Tokens = orml_tokens::Module<Runtime>;
+
+/// Prices every currency at `1.0`, so tests that don't care about the auction start price can
+/// ignore it.
+pub struct MockPriceSource;
+impl PriceProvider<CurrencyId, Ratio> for MockPriceSource {
+	fn get_price(_currency_id: CurrencyId) -> Option<Ratio> {
+		Some(Ratio::one())
+	}
+}
+
+parameter_types! {
+	pub const GetNativeCurrencyId: CurrencyId = XUSD;
+	pub const DEBTTreasuryModuleId: ModuleId = ModuleId(*b"aca/debt");
+	pub const MinimumCollateralAuctionSize: Balance = 100;
+	pub AuctionStartPremium: Ratio = Ratio::saturating_from_rational(1, 20);
+	pub AuctionMinimumPriceRatio: Ratio = Ratio::saturating_from_rational(1, 5);
+	pub const AuctionDuration: BlockNumber = 100;
+}
+
+impl Trait for Runtime {
+	type Event = TestEvent;
+	type Currency = Tokens;
+	type StableCurrencyId = GetNativeCurrencyId;
+	type PriceSource = MockPriceSource;
+	type MinimumCollateralAuctionSize = MinimumCollateralAuctionSize;
+	type AuctionStartPremium = AuctionStartPremium;
+	type AuctionMinimumPriceRatio = AuctionMinimumPriceRatio;
+	type AuctionDuration = AuctionDuration;
+	type ModuleId = DEBTTreasuryModuleId;
+}
+pub type DEBTTreasuryModule = Module<Runtime>;
+
+pub struct ExtBuilder;
+
+impl Default for ExtBuilder {
+	fn default() -> Self {
+		ExtBuilder
+	}
+}
+
+impl ExtBuilder {
+	pub fn build(self) -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default()
+			.build_storage::<Runtime>()
+			.unwrap();
+
+		orml_tokens::GenesisConfig::<Runtime> {
+			endowed_accounts: vec![(ALICE, BTC, 1000)],
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		t.into()
+	}
+}
@@ -0,0 +1,34 @@
+//! Runtime API definition for the lend module.
+//!
+//! Exposes read-only position queries that would otherwise require an off-chain client to
+//! replay `Positions`/`TotalPositions` storage and reimplement the debit-value and risk-ratio
+//! math itself.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use primitives::{Balance, CurrencyId};
+
+sp_api::decl_runtime_apis! {
+	/// Queries over a single collateralized debt position, for keeper bots and clients that need
+	/// to find unsafe positions without walking storage by hand.
+	pub trait LendApi<AccountId, Ratio> where
+		AccountId: Codec,
+		Ratio: Codec,
+	{
+		/// `(collateral, debit, collateral_value, debit_value, health_factor)` for `who`'s
+		/// position in `currency_id`. `health_factor` is
+		/// `collateral_value / (debit_value * required_collateral_ratio)`; a position is
+		/// liquidatable once it drops below `1`.
+		fn get_position(currency_id: CurrencyId, who: AccountId) -> (Balance, Balance, Balance, Balance, Ratio);
+
+		/// Whether `who`'s position in `currency_id` is currently unsafe, i.e. its health factor
+		/// has dropped below `1`.
+		fn is_position_unsafe(currency_id: CurrencyId, who: AccountId) -> bool;
+
+		/// The oracle price, in stablecoin per unit collateral, at which `who`'s position in
+		/// `currency_id` would cross the required collateral ratio. `None` if the position holds
+		/// no collateral.
+		fn get_liquidation_price(currency_id: CurrencyId, who: AccountId) -> Option<Ratio>;
+	}
+}
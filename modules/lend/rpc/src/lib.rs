@@ -0,0 +1,115 @@
+//! JSON-RPC frontend for the lend module's runtime API.
+
+use std::sync::Arc;
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result as RpcResult};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use lend_rpc_runtime_api::LendApi as LendRuntimeApi;
+use primitives::{Balance, CurrencyId};
+
+/// A position's collateral/debit, their stablecoin values, and the resulting health factor.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PositionInfo<Balance, Ratio> {
+	pub collateral: Balance,
+	pub debit: Balance,
+	pub collateral_value: Balance,
+	pub debit_value: Balance,
+	pub health_factor: Ratio,
+}
+
+#[rpc]
+pub trait LendApi<BlockHash, AccountId, Ratio> {
+	#[rpc(name = "lend_getPosition")]
+	fn get_position(
+		&self,
+		currency_id: CurrencyId,
+		who: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<PositionInfo<Balance, Ratio>>;
+
+	#[rpc(name = "lend_isPositionUnsafe")]
+	fn is_position_unsafe(&self, currency_id: CurrencyId, who: AccountId, at: Option<BlockHash>) -> RpcResult<bool>;
+
+	#[rpc(name = "lend_getLiquidationPrice")]
+	fn get_liquidation_price(
+		&self,
+		currency_id: CurrencyId,
+		who: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<Ratio>>;
+}
+
+/// Implements the `LendApi` JSON-RPC interface by delegating to the runtime's `LendApi` runtime
+/// API.
+pub struct Lend<C, B> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Lend<C, B> {
+	pub fn new(client: Arc<C>) -> Self {
+		Self {
+			client,
+			_marker: Default::default(),
+		}
+	}
+}
+
+fn runtime_error(message: impl ToString) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(1),
+		message: message.to_string(),
+		data: None,
+	}
+}
+
+impl<C, Block, AccountId, Ratio> LendApi<<Block as BlockT>::Hash, AccountId, Ratio> for Lend<C, Block>
+where
+	Block: BlockT,
+	C: Send + Sync + 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+	C::Api: LendRuntimeApi<Block, AccountId, Ratio>,
+	AccountId: Codec,
+	Ratio: Codec,
+{
+	fn get_position(
+		&self,
+		currency_id: CurrencyId,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<PositionInfo<Balance, Ratio>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		let (collateral, debit, collateral_value, debit_value, health_factor) =
+			api.get_position(&at, currency_id, who).map_err(runtime_error)?;
+		Ok(PositionInfo {
+			collateral,
+			debit,
+			collateral_value,
+			debit_value,
+			health_factor,
+		})
+	}
+
+	fn is_position_unsafe(&self, currency_id: CurrencyId, who: AccountId, at: Option<<Block as BlockT>::Hash>) -> RpcResult<bool> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.is_position_unsafe(&at, currency_id, who).map_err(runtime_error)
+	}
+
+	fn get_liquidation_price(
+		&self,
+		currency_id: CurrencyId,
+		who: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Option<Ratio>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		api.get_liquidation_price(&at, currency_id, who).map_err(runtime_error)
+	}
+}
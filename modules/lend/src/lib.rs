@@ -0,0 +1,514 @@
+//! # Lend Module
+//!
+//! ## Overview
+//!
+//! Tracks collateralized debt positions per `(CurrencyId, AccountId)`. A position's `debit` is
+//! stored in normalized units; the actual stablecoin owed by a position is
+//! `debit * DebitExchangeRate`. The exchange rate compounds every block by the collateral's
+//! stability fee, so interest accrues without ever rewriting existing positions' `debit` field.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, traits::Get, transactional};
+use frame_system::{ensure_root, ensure_signed};
+use orml_traits::{MultiCurrency, MultiCurrencyExtended};
+use primitives::{Amount, Balance, CurrencyId};
+use sp_runtime::{
+	traits::{AccountIdConversion, Saturating, Zero},
+	DispatchResult, FixedPointNumber, ModuleId, RuntimeDebug,
+};
+use sp_std::{convert::TryInto, vec::Vec};
+use support::{DEBTTreasury, DEXManager, OnFeeDeposit, PriceProvider, Ratio, RiskManager};
+
+/// Jump-rate model: `base_rate` plus `slope1` per unit of utilization up to `jump_utilization`,
+/// then a steeper `slope2` beyond it. All rates are annualized; `Module::stability_fee_per_block` divides the evaluated rate by
+/// `Trait::BlocksPerYear` before handing it to `accrue_interest`.
+#[derive(Encode, Decode, Clone, Copy, RuntimeDebug, Default, PartialEq, Eq)]
+pub struct InterestRateModel {
+	pub base_rate: Ratio,
+	pub jump_utilization: Ratio,
+	pub slope1: Ratio,
+	pub slope2: Ratio,
+}
+
+impl InterestRateModel {
+	/// Evaluate the annualized borrow rate at utilization `utilization`, which is first clamped
+	/// into `[0, 1]`.
+	fn annual_rate_at(&self, utilization: Ratio) -> Ratio {
+		let utilization = utilization.min(Ratio::one());
+		if utilization <= self.jump_utilization {
+			self.base_rate.saturating_add(utilization.saturating_mul(self.slope1))
+		} else {
+			let excess_utilization = utilization.saturating_sub(self.jump_utilization);
+			self.base_rate
+				.saturating_add(self.jump_utilization.saturating_mul(self.slope1))
+				.saturating_add(excess_utilization.saturating_mul(self.slope2))
+		}
+	}
+}
+
+mod mock;
+mod tests;
+
+/// A single collateralized debt position. `debit` is a normalized unit; the stablecoin value it
+/// represents is obtained via `Module::get_debit_value`.
+#[derive(Encode, Decode, Clone, Copy, RuntimeDebug, Default, PartialEq, Eq)]
+pub struct Position {
+	pub collateral: Balance,
+	pub debit: Balance,
+}
+
+pub trait Trait: frame_system::Trait {
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Trait>::Event>;
+	type Currency: MultiCurrencyExtended<Self::AccountId, CurrencyId = CurrencyId, Balance = Balance, Amount = Amount>;
+	type StableCurrencyId: Get<CurrencyId>;
+	type RiskManager: RiskManager<Self::AccountId, CurrencyId, Balance>;
+	type DEBTTreasury: DEBTTreasury<Self::AccountId, Balance = Balance, CurrencyId = CurrencyId>;
+	/// Routes a liquidation penalty's stablecoin value (e.g. to a treasury or buyback account).
+	type OnFeeDeposit: OnFeeDeposit<Balance>;
+	/// Prices collateral in stablecoin terms, for utilization and risk checks.
+	type PriceSource: PriceProvider<CurrencyId, Ratio>;
+	/// Swaps collateral for stablecoin on `close_loan_by_dex`'s behalf.
+	type DEX: DEXManager<Self::AccountId, CurrencyId, Balance>;
+	/// How far `close_loan_by_dex`'s swap price is allowed to stray above the oracle price.
+	type MaxSlippageSwapWithDEX: Get<Ratio>;
+	/// Collaterals whose debit exchange rate is compounded on `on_initialize`.
+	type CollateralCurrencyIds: Get<Vec<CurrencyId>>;
+	/// Used to convert an `InterestRateModel`'s annualized rate into a per-block rate.
+	type BlocksPerYear: Get<Balance>;
+	type ModuleId: Get<ModuleId>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Lend {
+		/// Open positions, keyed by collateral currency then owner.
+		pub Positions get(fn positions): double_map hasher(twox_64_concat) CurrencyId, hasher(twox_64_concat) T::AccountId => Position;
+
+		/// Sum of all positions' collateral/debit for a currency, kept in lock-step with
+		/// `Positions` so ref-counting and risk checks don't need to iterate every account.
+		pub TotalPositions get(fn total_positions): map hasher(twox_64_concat) CurrencyId => Position;
+
+		/// How much stablecoin one unit of normalized `debit` currently owes. Only ever moves
+		/// forward via `accrue_interest`; positions' stored `debit` is never rescaled.
+		pub DebitExchangeRate get(fn debit_exchange_rate): map hasher(twox_64_concat) CurrencyId => Option<Ratio>;
+
+		/// Stability fee charged on top of `StabilityFee`, per collateral. Ignored once a
+		/// collateral has an `InterestRateModel` configured.
+		pub CollateralStabilityFee get(fn collateral_stability_fee): map hasher(twox_64_concat) CurrencyId => Option<Ratio>;
+
+		/// Global per-block stability fee applied to collaterals with no `InterestRateModel`.
+		pub StabilityFee get(fn stability_fee) config(): Ratio;
+
+		/// Jump-rate curve used to derive the per-block stability fee from utilization, per
+		/// collateral. Collaterals without one fall back to `StabilityFee` + `CollateralStabilityFee`.
+		pub InterestRateModels get(fn interest_rate_model): map hasher(twox_64_concat) CurrencyId => Option<InterestRateModel>;
+
+		/// Extra fraction of a liquidated position's bad debt charged as a penalty, per collateral.
+		pub LiquidationPenalty get(fn liquidation_penalty): map hasher(twox_64_concat) CurrencyId => Option<Ratio>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T>
+	where
+		<T as frame_system::Trait>::AccountId,
+	{
+		/// A position was updated to the given collateral/debit totals.
+		PositionUpdated(AccountId, CurrencyId, Balance, Balance),
+		/// A position's debit/collateral moved from one owner to another.
+		TransferLoan(AccountId, AccountId, CurrencyId),
+		/// Collateral and debit were seized from a position into the debt treasury, along with
+		/// the stablecoin liquidation penalty charged on top of the bad debt.
+		ConfiscateCollateralAndDebit(AccountId, CurrencyId, Balance, Balance, Balance),
+		/// Debit was repaid by swapping collateral through the DEX: collateral consumed, then
+		/// debit (normalized) repaid.
+		ClosedLoanByDex(AccountId, CurrencyId, Balance, Balance),
+	}
+);
+
+decl_error! {
+	pub enum Error for Module<T: Trait> {
+		/// Collateral adjustment would underflow the position's collateral.
+		CollateralTooLow,
+		/// Debit adjustment would underflow the position's debit.
+		DebitTooLow,
+		/// Adjustment would overflow position or total storage.
+		AmountConvertFailed,
+		/// The resulting position fails the risk manager's validity check.
+		InvalidPosition,
+		/// `close_loan_by_dex` was called with no outstanding debit to repay.
+		InvalidDebitDecrease,
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		type Error = Error<T>;
+
+		fn deposit_event() = default;
+
+		fn on_initialize(_now: T::BlockNumber) -> frame_support::weights::Weight {
+			for currency_id in T::CollateralCurrencyIds::get() {
+				Self::accrue_interest(currency_id);
+			}
+			0
+		}
+
+		/// Set or update a collateral's jump-rate curve. Root only.
+		#[weight = 10_000]
+		pub fn set_interest_rate_model(origin, currency_id: CurrencyId, model: InterestRateModel) {
+			ensure_root(origin)?;
+			InterestRateModels::insert(currency_id, model);
+		}
+
+		/// Set or update a collateral's liquidation penalty. Root only.
+		#[weight = 10_000]
+		pub fn set_liquidation_penalty(origin, currency_id: CurrencyId, penalty: Ratio) {
+			ensure_root(origin)?;
+			LiquidationPenalty::insert(currency_id, penalty);
+		}
+
+		/// Set or update a collateral's extra stability fee, charged on top of `StabilityFee`
+		/// while it has no `InterestRateModel` configured. Root only.
+		#[weight = 10_000]
+		pub fn set_collateral_stability_fee(origin, currency_id: CurrencyId, fee: Ratio) {
+			ensure_root(origin)?;
+			CollateralStabilityFee::insert(currency_id, fee);
+		}
+
+		/// Repay up to `debit_decrease` (normalized) of the caller's `currency_id` debit by
+		/// swapping some of that position's own collateral through `T::DEX` for stablecoin,
+		/// rather than requiring the caller to already hold it. The swap is capped at
+		/// `max_collateral_amount` and at the oracle price inflated by
+		/// `T::MaxSlippageSwapWithDEX`; it fails cleanly if the DEX can't fill within that bound.
+		#[weight = 10_000]
+		pub fn repay_with_collateral(
+			origin,
+			currency_id: CurrencyId,
+			debit_decrease: Balance,
+			max_collateral_amount: Balance,
+		) {
+			let who = ensure_signed(origin)?;
+			Self::close_loan_by_dex(&who, currency_id, debit_decrease, max_collateral_amount)?;
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	pub fn account_id() -> T::AccountId {
+		T::ModuleId::get().into_account()
+	}
+
+	/// The stablecoin value currently owed for `debit` normalized units of `currency_id`'s debit.
+	pub fn get_debit_value(currency_id: CurrencyId, debit: Balance) -> Balance {
+		Self::get_debit_exchange_rate(currency_id).saturating_mul_int(debit)
+	}
+
+	fn get_debit_exchange_rate(currency_id: CurrencyId) -> Ratio {
+		Self::debit_exchange_rate(currency_id).unwrap_or_else(Ratio::one)
+	}
+
+	/// The per-block stability fee applied while compounding `currency_id`'s debit exchange rate.
+	/// Collaterals with an `InterestRateModel` derive it from utilization; others fall back to
+	/// the flat `StabilityFee` + `CollateralStabilityFee`.
+	fn stability_fee_per_block(currency_id: CurrencyId) -> Ratio {
+		match Self::interest_rate_model(currency_id) {
+			Some(model) => model
+				.annual_rate_at(Self::utilization(currency_id))
+				.saturating_mul(Ratio::saturating_from_rational(1, T::BlocksPerYear::get())),
+			None => Self::stability_fee().saturating_add(Self::collateral_stability_fee(currency_id).unwrap_or_default()),
+		}
+	}
+
+	/// `total_debit_value / total_collateral_value` for `currency_id`, clamped to `[0, 1]`.
+	fn utilization(currency_id: CurrencyId) -> Ratio {
+		let total_positions = Self::total_positions(currency_id);
+		let total_collateral_value = T::PriceSource::get_price(currency_id)
+			.unwrap_or_else(Ratio::zero)
+			.saturating_mul_int(total_positions.collateral);
+		if total_collateral_value.is_zero() {
+			return Ratio::zero();
+		}
+
+		let total_debit_value = Self::get_debit_value(currency_id, total_positions.debit);
+		Ratio::saturating_from_rational(total_debit_value, total_collateral_value).min(Ratio::one())
+	}
+
+	/// Compound `currency_id`'s debit exchange rate by one block's worth of stability fee,
+	/// minting the newly-accrued value as surplus into the debt treasury.
+	fn accrue_interest(currency_id: CurrencyId) {
+		let stability_fee_per_block = Self::stability_fee_per_block(currency_id);
+		if stability_fee_per_block.is_zero() {
+			return;
+		}
+
+		let old_rate = Self::get_debit_exchange_rate(currency_id);
+		let new_rate = old_rate.saturating_mul(Ratio::one().saturating_add(stability_fee_per_block));
+		let total_debit = Self::total_positions(currency_id).debit;
+
+		if !total_debit.is_zero() {
+			let accrued = new_rate
+				.saturating_sub(old_rate)
+				.saturating_mul_int(total_debit);
+			if !accrued.is_zero() {
+				T::DEBTTreasury::on_system_surplus(accrued);
+			}
+		}
+
+		DebitExchangeRate::insert(currency_id, new_rate);
+	}
+
+	/// Adjust `who`'s collateral/debit, transferring the collateral delta and minting/burning the
+	/// stablecoin represented by the debit delta, after the risk manager approves the result.
+	#[transactional]
+	pub fn adjust_position(
+		who: &T::AccountId,
+		currency_id: CurrencyId,
+		collateral_adjustment: Amount,
+		debit_adjustment: Amount,
+	) -> DispatchResult {
+		Self::update_loan(who, currency_id, collateral_adjustment, debit_adjustment)?;
+
+		let collateral_balance_adjustment = Self::balance_try_from_amount_abs(collateral_adjustment)?;
+		let module_account = Self::account_id();
+		if collateral_adjustment.is_positive() {
+			T::Currency::transfer(currency_id, who, &module_account, collateral_balance_adjustment)?;
+		} else if collateral_adjustment.is_negative() {
+			T::Currency::transfer(currency_id, &module_account, who, collateral_balance_adjustment)?;
+		}
+
+		let debit_balance_adjustment = Self::balance_try_from_amount_abs(debit_adjustment)?;
+		let debit_value_adjustment = Self::get_debit_value(currency_id, debit_balance_adjustment);
+		if debit_adjustment.is_positive() {
+			T::Currency::deposit(T::StableCurrencyId::get(), who, debit_value_adjustment)?;
+		} else if debit_adjustment.is_negative() {
+			T::Currency::withdraw(T::StableCurrencyId::get(), who, debit_value_adjustment)?;
+		}
+
+		let position = Self::positions(currency_id, who);
+		let debit_value = Self::get_debit_value(currency_id, position.debit);
+		T::RiskManager::check_position_valid(currency_id, position.collateral, debit_value)
+			.map_err(|_| Error::<T>::InvalidPosition)?;
+
+		Ok(())
+	}
+
+	/// Move `from`'s entire position at `currency_id` onto `to`'s position.
+	pub fn transfer_loan(from: &T::AccountId, to: &T::AccountId, currency_id: CurrencyId) -> DispatchResult {
+		let Position { collateral, debit } = Self::positions(currency_id, from);
+		let collateral_adjustment = Self::amount_try_from_balance(collateral)?;
+		let debit_adjustment = Self::amount_try_from_balance(debit)?;
+
+		Self::update_loan(from, currency_id, collateral_adjustment.saturating_neg(), debit_adjustment.saturating_neg())?;
+		Self::update_loan(to, currency_id, collateral_adjustment, debit_adjustment)?;
+
+		Self::deposit_event(RawEvent::TransferLoan(from.clone(), to.clone(), currency_id));
+		Ok(())
+	}
+
+	/// Seize `collateral_confiscate` collateral and `debit_decrease` (normalized) debit from
+	/// `who`'s position into the debt treasury.
+	pub fn confiscate_collateral_and_debit(
+		who: &T::AccountId,
+		currency_id: CurrencyId,
+		collateral_confiscate: Balance,
+		debit_decrease: Balance,
+	) -> DispatchResult {
+		let bad_debt_value = Self::get_debit_value(currency_id, debit_decrease);
+		let penalty = Self::liquidation_penalty(currency_id)
+			.unwrap_or_default()
+			.saturating_mul_int(bad_debt_value);
+		let target = bad_debt_value.saturating_add(penalty);
+
+		T::DEBTTreasury::create_collateral_auction(&Self::account_id(), who, currency_id, collateral_confiscate, target)?;
+		// `target` covers both the bad debt and the penalty minted below, so the treasury's
+		// books balance and the penalty is only actually paid off once liquidation completes.
+		T::DEBTTreasury::on_system_debit(target);
+		if !penalty.is_zero() {
+			T::OnFeeDeposit::on_fee_deposit(penalty)?;
+		}
+
+		Self::update_loan(
+			who,
+			currency_id,
+			Self::amount_try_from_balance(collateral_confiscate)?.saturating_neg(),
+			Self::amount_try_from_balance(debit_decrease)?.saturating_neg(),
+		)?;
+
+		Self::deposit_event(RawEvent::ConfiscateCollateralAndDebit(
+			who.clone(),
+			currency_id,
+			collateral_confiscate,
+			debit_decrease,
+			penalty,
+		));
+		Ok(())
+	}
+
+	/// Repay up to `debit_decrease` (normalized) of `who`'s `currency_id` debit by swapping that
+	/// position's own collateral, held by the module account, through `T::DEX` for stablecoin,
+	/// then burning it and reducing collateral/debit in one step. The swap's supply side is
+	/// capped at `max_collateral_amount` and at the oracle price inflated by
+	/// `T::MaxSlippageSwapWithDEX`.
+	pub fn close_loan_by_dex(
+		who: &T::AccountId,
+		currency_id: CurrencyId,
+		debit_decrease: Balance,
+		max_collateral_amount: Balance,
+	) -> DispatchResult {
+		let position = Self::positions(currency_id, who);
+		let debit_decrease = debit_decrease.min(position.debit);
+		ensure!(!debit_decrease.is_zero(), Error::<T>::InvalidDebitDecrease);
+
+		let debit_value = Self::get_debit_value(currency_id, debit_decrease);
+		let oracle_supply_amount = T::PriceSource::get_price(currency_id)
+			.unwrap_or_else(Ratio::zero)
+			.reciprocal()
+			.unwrap_or_else(Ratio::zero)
+			.saturating_mul_int(debit_value);
+		let max_supply_amount = Ratio::one()
+			.saturating_add(T::MaxSlippageSwapWithDEX::get())
+			.saturating_mul_int(oracle_supply_amount)
+			.min(max_collateral_amount)
+			.min(position.collateral);
+
+		let module_account = Self::account_id();
+		let actual_supply_amount = T::DEX::swap_with_exact_target(
+			&module_account,
+			&[currency_id, T::StableCurrencyId::get()],
+			debit_value,
+			max_supply_amount,
+		)?;
+		T::Currency::withdraw(T::StableCurrencyId::get(), &module_account, debit_value)?;
+
+		Self::update_loan(
+			who,
+			currency_id,
+			Self::amount_try_from_balance(actual_supply_amount)?.saturating_neg(),
+			Self::amount_try_from_balance(debit_decrease)?.saturating_neg(),
+		)?;
+
+		let position = Self::positions(currency_id, who);
+		let debit_value = Self::get_debit_value(currency_id, position.debit);
+		T::RiskManager::check_position_valid(currency_id, position.collateral, debit_value)
+			.map_err(|_| Error::<T>::InvalidPosition)?;
+
+		Self::deposit_event(RawEvent::ClosedLoanByDex(who.clone(), currency_id, actual_supply_amount, debit_decrease));
+		Ok(())
+	}
+
+	/// Apply a raw collateral/debit delta to `who`'s position and to `TotalPositions`, without
+	/// moving any balances or checking validity. `debit_adjustment` is in normalized units.
+	pub fn update_loan(
+		who: &T::AccountId,
+		currency_id: CurrencyId,
+		collateral_adjustment: Amount,
+		debit_adjustment: Amount,
+	) -> DispatchResult {
+		let position = Self::positions(currency_id, who);
+
+		let collateral_balance = Self::balance_try_from_amount_abs(collateral_adjustment)?;
+		let debit_balance = Self::balance_try_from_amount_abs(debit_adjustment)?;
+
+		let new_collateral = if collateral_adjustment.is_negative() {
+			position.collateral.checked_sub(collateral_balance).ok_or(Error::<T>::CollateralTooLow)?
+		} else {
+			position.collateral.saturating_add(collateral_balance)
+		};
+		let new_debit = if debit_adjustment.is_negative() {
+			position.debit.checked_sub(debit_balance).ok_or(Error::<T>::DebitTooLow)?
+		} else {
+			position.debit.saturating_add(debit_balance)
+		};
+
+		let was_open = position.collateral != Zero::zero() || position.debit != Zero::zero();
+		let is_open = new_collateral != Zero::zero() || new_debit != Zero::zero();
+		if !was_open && is_open {
+			frame_system::Module::<T>::inc_ref(who);
+		} else if was_open && !is_open {
+			frame_system::Module::<T>::dec_ref(who);
+		}
+
+		TotalPositions::mutate(currency_id, |total| {
+			total.collateral = if collateral_adjustment.is_negative() {
+				total.collateral.saturating_sub(collateral_balance)
+			} else {
+				total.collateral.saturating_add(collateral_balance)
+			};
+			total.debit = if debit_adjustment.is_negative() {
+				total.debit.saturating_sub(debit_balance)
+			} else {
+				total.debit.saturating_add(debit_balance)
+			};
+		});
+
+		if is_open {
+			Positions::<T>::insert(currency_id, who, Position {
+				collateral: new_collateral,
+				debit: new_debit,
+			});
+		} else {
+			Positions::<T>::remove(currency_id, who);
+		}
+
+		Self::deposit_event(RawEvent::PositionUpdated(who.clone(), currency_id, new_collateral, new_debit));
+		Ok(())
+	}
+
+	fn balance_try_from_amount_abs(amount: Amount) -> Result<Balance, Error<T>> {
+		TryInto::<Balance>::try_into(amount.saturating_abs()).map_err(|_| Error::<T>::AmountConvertFailed)
+	}
+
+	fn amount_try_from_balance(balance: Balance) -> Result<Amount, Error<T>> {
+		TryInto::<Amount>::try_into(balance).map_err(|_| Error::<T>::AmountConvertFailed)
+	}
+
+	/// `(collateral, debit, collateral_value, debit_value, health_factor)` for `who`'s position
+	/// in `currency_id`, as surfaced by the lend runtime API. `health_factor` is
+	/// `collateral_value / (debit_value * required_collateral_ratio)`, saturating to
+	/// `Ratio::max_value()` for a debt-free position.
+	pub fn get_position(currency_id: CurrencyId, who: T::AccountId) -> (Balance, Balance, Balance, Balance, Ratio) {
+		let Position { collateral, debit } = Self::positions(currency_id, &who);
+		let collateral_value = T::PriceSource::get_price(currency_id)
+			.unwrap_or_else(Ratio::zero)
+			.saturating_mul_int(collateral);
+		let debit_value = Self::get_debit_value(currency_id, debit);
+
+		let health_factor = if debit_value.is_zero() {
+			Ratio::max_value()
+		} else {
+			let required_collateral_value = T::RiskManager::required_collateral_ratio(currency_id)
+				.unwrap_or_else(Ratio::one)
+				.saturating_mul_int(debit_value);
+			if required_collateral_value.is_zero() {
+				Ratio::max_value()
+			} else {
+				Ratio::saturating_from_rational(collateral_value, required_collateral_value)
+			}
+		};
+
+		(collateral, debit, collateral_value, debit_value, health_factor)
+	}
+
+	/// Whether `who`'s position in `currency_id` has dropped below its required collateral ratio.
+	pub fn is_position_unsafe(currency_id: CurrencyId, who: T::AccountId) -> bool {
+		let (_, debit, _, _, health_factor) = Self::get_position(currency_id, who);
+		!debit.is_zero() && health_factor < Ratio::one()
+	}
+
+	/// The oracle price, in stablecoin per unit collateral, at which `who`'s position in
+	/// `currency_id` would cross its required collateral ratio. `None` if the position holds no
+	/// collateral.
+	pub fn get_liquidation_price(currency_id: CurrencyId, who: T::AccountId) -> Option<Ratio> {
+		let Position { collateral, debit } = Self::positions(currency_id, &who);
+		if collateral.is_zero() {
+			return None;
+		}
+
+		let debit_value = Self::get_debit_value(currency_id, debit);
+		let required_ratio = T::RiskManager::required_collateral_ratio(currency_id).unwrap_or_else(Ratio::one);
+		let required_collateral_value = required_ratio.saturating_mul_int(debit_value);
+		Some(Ratio::saturating_from_rational(required_collateral_value, collateral))
+	}
+}
@@ -0,0 +1,295 @@
+//! Mocks for the lend module.
+
+#![cfg(test)]
+
+use super::*;
+use frame_support::{impl_outer_dispatch, impl_outer_event, impl_outer_origin, parameter_types};
+use primitives::TokenSymbol;
+use sp_core::H256;
+use sp_runtime::{testing::Header, traits::IdentityLookup, DispatchError, DispatchResult, Perbill};
+use support::{DEXManager, OnFeeDeposit, PriceProvider, RiskManager};
+
+pub type AccountId = u128;
+pub type BlockNumber = u64;
+
+pub const ALICE: AccountId = 1;
+pub const BOB: AccountId = 2;
+pub const BTC: CurrencyId = CurrencyId::Token(TokenSymbol::XBTC);
+pub const DOT: CurrencyId = CurrencyId::Token(TokenSymbol::DOT);
+pub const XUSD: CurrencyId = CurrencyId::Token(TokenSymbol::XUSD);
+
+/// Debit value cap enforced by `MockRiskManager` for `BTC` positions, used to exercise the
+/// "exceed debit value cap" failure path without a real risk-params storage.
+pub const BTC_MAX_DEBIT_VALUE: Balance = 800;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Runtime;
+
+impl_outer_origin! {
+	pub enum Origin for Runtime {}
+}
+
+impl_outer_dispatch! {
+	pub enum Call for Runtime where origin: Origin {
+		orml_currencies::Currencies,
+		frame_system::System,
+	}
+}
+
+impl_outer_event! {
+	pub enum TestEvent for Runtime {
+		frame_system<T>,
+		orml_tokens<T>,
+		pallet_balances<T>,
+		orml_currencies<T>,
+		debt_treasury<T>,
+		lend<T>,
+	}
+}
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const MaximumBlockWeight: u32 = 1024;
+	pub const MaximumBlockLength: u32 = 2 * 1024;
+	pub const AvailableBlockRatio: Perbill = Perbill::one();
+}
+
+impl frame_system::Trait for Runtime {
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Call = Call;
+	type Hash = H256;
+	type Hashing = ::sp_runtime::traits::BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = TestEvent;
+	type BlockHashCount = BlockHashCount;
+	type MaximumBlockWeight = MaximumBlockWeight;
+	type MaximumBlockLength = MaximumBlockLength;
+	type AvailableBlockRatio = AvailableBlockRatio;
+	type Version = ();
+	type PalletInfo = ();
+	type AccountData = pallet_balances::AccountData<Balance>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type DbWeight = ();
+	type BlockExecutionWeight = ();
+	type ExtrinsicBaseWeight = ();
+	type MaximumExtrinsicWeight = ();
+	type BaseCallFilter = ();
+	type SystemWeightInfo = ();
+}
+pub type System = frame_system::Module<Runtime>;
+
+impl orml_tokens::Trait for Runtime {
+	type Event = TestEvent;
+	type Balance = Balance;
+	type Amount = Amount;
+	type CurrencyId = CurrencyId;
+	type OnReceived = ();
+	type WeightInfo = ();
+}
+pub type Tokens = orml_tokens::Module<Runtime>;
+
+parameter_types! {
+	pub const ExistentialDeposit: Balance = 0;
+}
+
+impl pallet_balances::Trait for Runtime {
+	type Balance = Balance;
+	type DustRemoval = ();
+	type Event = TestEvent;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = frame_system::Module<Runtime>;
+	type MaxLocks = ();
+	type WeightInfo = ();
+}
+pub type PalletBalances = pallet_balances::Module<Runtime>;
+
+pub type AdaptedBasicCurrency = orml_currencies::BasicCurrencyAdapter<Runtime, PalletBalances, Amount, BlockNumber>;
+
+parameter_types! {
+	pub const GetNativeCurrencyId: CurrencyId = XUSD;
+}
+
+impl orml_currencies::Trait for Runtime {
+	type Event = TestEvent;
+	type MultiCurrency = Tokens;
+	type NativeCurrency = AdaptedBasicCurrency;
+	type GetNativeCurrencyId = GetNativeCurrencyId;
+	type WeightInfo = ();
+}
+pub type Currencies = orml_currencies::Module<Runtime>;
+
+parameter_types! {
+	pub const DEBTTreasuryModuleId: ModuleId = ModuleId(*b"aca/debt");
+	pub const MinimumCollateralAuctionSize: Balance = 1000;
+	pub AuctionStartPremium: Ratio = Ratio::saturating_from_rational(1, 20);
+	pub AuctionMinimumPriceRatio: Ratio = Ratio::saturating_from_rational(1, 5);
+	pub const AuctionDuration: BlockNumber = 100;
+}
+
+impl debt_treasury::Trait for Runtime {
+	type Event = TestEvent;
+	type Currency = Currencies;
+	type StableCurrencyId = GetNativeCurrencyId;
+	type PriceSource = MockPriceSource;
+	type MinimumCollateralAuctionSize = MinimumCollateralAuctionSize;
+	type AuctionStartPremium = AuctionStartPremium;
+	type AuctionMinimumPriceRatio = AuctionMinimumPriceRatio;
+	type AuctionDuration = AuctionDuration;
+	type ModuleId = DEBTTreasuryModuleId;
+}
+pub type DEBTTreasuryModule = debt_treasury::Module<Runtime>;
+
+/// `BTC`'s required collateral ratio, used by `MockRiskManager::required_collateral_ratio` and
+/// the position health-factor queries.
+pub const BTC_REQUIRED_COLLATERAL_RATIO: (u128, u128) = (3, 2);
+
+/// Rejects `DOT` positions outright and, for `BTC`, caps debit value and enforces
+/// `required_collateral_ratio`, mirroring just enough of the real risk manager to exercise
+/// `adjust_position`/`close_loan_by_dex`'s validity checks.
+pub struct MockRiskManager;
+impl RiskManager<AccountId, CurrencyId, Balance> for MockRiskManager {
+	fn check_position_valid(currency_id: CurrencyId, collateral: Balance, debit_value: Balance) -> DispatchResult {
+		if currency_id == DOT {
+			return Err(DispatchError::Other("mock risk manager: DOT is disabled"));
+		}
+		if currency_id == BTC {
+			if debit_value > BTC_MAX_DEBIT_VALUE {
+				return Err(DispatchError::Other("mock risk manager: debit value cap exceeded"));
+			}
+			if !debit_value.is_zero() {
+				let collateral_value = MockPriceSource::get_price(BTC)
+					.unwrap_or_else(Ratio::zero)
+					.saturating_mul_int(collateral);
+				let required_collateral_value = Ratio::saturating_from_rational(
+					BTC_REQUIRED_COLLATERAL_RATIO.0,
+					BTC_REQUIRED_COLLATERAL_RATIO.1,
+				)
+				.saturating_mul_int(debit_value);
+				if collateral_value < required_collateral_value {
+					return Err(DispatchError::Other("mock risk manager: below required collateral ratio"));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	fn required_collateral_ratio(currency_id: CurrencyId) -> Option<Ratio> {
+		if currency_id == BTC {
+			Some(Ratio::saturating_from_rational(
+				BTC_REQUIRED_COLLATERAL_RATIO.0,
+				BTC_REQUIRED_COLLATERAL_RATIO.1,
+			))
+		} else {
+			None
+		}
+	}
+}
+
+/// Prices every currency at `1.0`, so tests that don't care about utilization can ignore it.
+pub struct MockPriceSource;
+impl PriceProvider<CurrencyId, Ratio> for MockPriceSource {
+	fn get_price(_currency_id: CurrencyId) -> Option<Ratio> {
+		Some(Ratio::one())
+	}
+}
+
+/// Account that liquidation penalties are minted into, standing in for a real buyback/treasury
+/// destination.
+pub const FEE_ACCOUNT: AccountId = 100;
+
+pub struct MockOnFeeDeposit;
+impl OnFeeDeposit<Balance> for MockOnFeeDeposit {
+	fn on_fee_deposit(amount: Balance) -> DispatchResult {
+		Currencies::deposit(XUSD, &FEE_ACCOUNT, amount)
+	}
+}
+
+/// Always fills by consuming the caller's entire `max_supply_amount` budget to deliver
+/// `target_amount`, i.e. worst-case slippage within whatever bound the caller passed in, so
+/// `close_loan_by_dex` tests can exercise what happens when a swap executes right at the
+/// slippage ceiling.
+pub struct MockDEX;
+impl DEXManager<AccountId, CurrencyId, Balance> for MockDEX {
+	fn swap_with_exact_target(
+		who: &AccountId,
+		path: &[CurrencyId],
+		target_amount: Balance,
+		max_supply_amount: Balance,
+	) -> sp_std::result::Result<Balance, DispatchError> {
+		if target_amount > max_supply_amount {
+			return Err(DispatchError::Other("mock dex: exceeds max supply amount"));
+		}
+
+		Currencies::withdraw(path[0], who, max_supply_amount)?;
+		Currencies::deposit(path[path.len() - 1], who, target_amount)?;
+		Ok(max_supply_amount)
+	}
+}
+
+parameter_types! {
+	pub const LendModuleId: ModuleId = ModuleId(*b"aca/lend");
+	pub CollateralCurrencyIds: Vec<CurrencyId> = vec![BTC, DOT];
+	pub const BlocksPerYear: Balance = 5_256_000;
+	// Deliberately generous so tests can drive a swap past the required collateral ratio's
+	// margin of safety; see `close_loan_by_dex_rejects_swap_that_leaves_position_below_required_ratio`.
+	pub MaxSlippageSwapWithDEX: Ratio = Ratio::saturating_from_rational(3, 5);
+}
+
+impl Trait for Runtime {
+	type Event = TestEvent;
+	type Currency = Currencies;
+	type StableCurrencyId = GetNativeCurrencyId;
+	type RiskManager = MockRiskManager;
+	type DEBTTreasury = DEBTTreasuryModule;
+	type OnFeeDeposit = MockOnFeeDeposit;
+	type PriceSource = MockPriceSource;
+	type DEX = MockDEX;
+	type MaxSlippageSwapWithDEX = MaxSlippageSwapWithDEX;
+	type CollateralCurrencyIds = CollateralCurrencyIds;
+	type BlocksPerYear = BlocksPerYear;
+	type ModuleId = LendModuleId;
+}
+pub type LendModule = Module<Runtime>;
+
+pub struct ExtBuilder {
+	endowed_accounts: Vec<(AccountId, CurrencyId, Balance)>,
+}
+
+impl Default for ExtBuilder {
+	fn default() -> Self {
+		Self {
+			endowed_accounts: vec![(ALICE, BTC, 1000)],
+		}
+	}
+}
+
+impl ExtBuilder {
+	pub fn build(self) -> sp_io::TestExternalities {
+		let mut t = frame_system::GenesisConfig::default()
+			.build_storage::<Runtime>()
+			.unwrap();
+
+		pallet_balances::GenesisConfig::<Runtime> { balances: vec![] }
+			.assimilate_storage(&mut t)
+			.unwrap();
+
+		orml_tokens::GenesisConfig::<Runtime> {
+			endowed_accounts: self.endowed_accounts,
+		}
+		.assimilate_storage(&mut t)
+		.unwrap();
+
+		GenesisConfig {
+			stability_fee: Ratio::zero(),
+		}
+		.assimilate_storage::<Runtime>(&mut t)
+		.unwrap();
+
+		t.into()
+	}
+}
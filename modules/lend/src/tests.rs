@@ -5,16 +5,292 @@
 use super::*;
 use frame_support::{assert_noop, assert_ok};
 use mock::{
-	Currencies, DEBTTreasuryModule, ExtBuilder, LendModule, Runtime, System, TestEvent, ALICE, BOB, BTC, DOT, XUSD,
+	Currencies, DEBTTreasuryModule, ExtBuilder, LendModule, Origin, Runtime, System, TestEvent, ALICE, BOB, BTC, DOT,
+	FEE_ACCOUNT, XUSD,
 };
+use sp_runtime::DispatchError;
+
+#[test]
+fn annual_rate_at_follows_jump_rate_curve() {
+	let model = InterestRateModel {
+		base_rate: Ratio::saturating_from_rational(2, 100),
+		jump_utilization: Ratio::saturating_from_rational(8, 10),
+		slope1: Ratio::saturating_from_rational(10, 100),
+		slope2: Ratio::saturating_from_rational(100, 100),
+	};
+
+	// at or below the jump: base_rate + utilization * slope1.
+	assert_eq!(
+		model.annual_rate_at(Ratio::saturating_from_rational(4, 10)),
+		Ratio::saturating_from_rational(2, 100)
+			.saturating_add(Ratio::saturating_from_rational(4, 10).saturating_mul(Ratio::saturating_from_rational(10, 100))),
+	);
+
+	// above the jump, the curve continues from the same point but at the steeper slope2.
+	let at_jump = model.annual_rate_at(model.jump_utilization);
+	let above_jump = model.annual_rate_at(Ratio::saturating_from_rational(9, 10));
+	assert_eq!(
+		above_jump,
+		at_jump.saturating_add(
+			Ratio::saturating_from_rational(1, 10).saturating_mul(Ratio::saturating_from_rational(100, 100))
+		),
+	);
+
+	// utilization above 100% is clamped to 1 rather than extrapolating further.
+	assert_eq!(
+		model.annual_rate_at(Ratio::one()),
+		model.annual_rate_at(Ratio::saturating_from_rational(2, 1)),
+	);
+}
+
+#[test]
+fn set_interest_rate_model_requires_root() {
+	ExtBuilder::default().build().execute_with(|| {
+		let model = InterestRateModel {
+			base_rate: Ratio::zero(),
+			jump_utilization: Ratio::saturating_from_rational(8, 10),
+			slope1: Ratio::saturating_from_rational(1, 10),
+			slope2: Ratio::one(),
+		};
+
+		assert_noop!(
+			LendModule::set_interest_rate_model(Origin::signed(ALICE), BTC, model),
+			DispatchError::BadOrigin,
+		);
+		assert_eq!(LendModule::interest_rate_model(BTC), None);
+
+		assert_ok!(LendModule::set_interest_rate_model(Origin::root(), BTC, model));
+		assert_eq!(LendModule::interest_rate_model(BTC), Some(model));
+	});
+}
+
+#[test]
+fn set_collateral_stability_fee_requires_root_and_stacks_with_stability_fee() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		<StabilityFee<Runtime>>::put(Ratio::saturating_from_rational(1, 100));
+
+		assert_noop!(
+			LendModule::set_collateral_stability_fee(
+				Origin::signed(ALICE),
+				BTC,
+				Ratio::saturating_from_rational(1, 100)
+			),
+			DispatchError::BadOrigin,
+		);
+		assert_eq!(LendModule::collateral_stability_fee(BTC), None);
+
+		assert_ok!(LendModule::set_collateral_stability_fee(
+			Origin::root(),
+			BTC,
+			Ratio::saturating_from_rational(1, 100)
+		));
+		assert_eq!(
+			LendModule::collateral_stability_fee(BTC),
+			Some(Ratio::saturating_from_rational(1, 100))
+		);
+
+		// the two fees stack: 1% `StabilityFee` + 1% `CollateralStabilityFee` = 2% this block.
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 1000, 100));
+		LendModule::on_initialize(1);
+		assert_eq!(
+			LendModule::debit_exchange_rate(BTC),
+			Some(Ratio::saturating_from_rational(102, 100))
+		);
+	});
+}
+
+#[test]
+fn utilization_drives_interest_rate_model_selection() {
+	ExtBuilder::default().build().execute_with(|| {
+		// 500 debit_value out of 1000 collateral_value (price 1.0): 50% utilization.
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 1000, 500));
+		assert_eq!(LendModule::utilization(BTC), Ratio::saturating_from_rational(1, 2));
+
+		let model = InterestRateModel {
+			base_rate: Ratio::zero(),
+			jump_utilization: Ratio::saturating_from_rational(8, 10),
+			slope1: Ratio::saturating_from_rational(10, 100),
+			slope2: Ratio::one(),
+		};
+		assert_ok!(LendModule::set_interest_rate_model(Origin::root(), BTC, model));
+
+		// below the jump: base_rate(0) + utilization(1/2) * slope1(1/10) = 1/20.
+		assert_eq!(
+			model.annual_rate_at(LendModule::utilization(BTC)),
+			Ratio::saturating_from_rational(1, 20),
+		);
+
+		// push utilization above the jump by adding more debit with no extra collateral.
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 0, 400));
+		assert_eq!(LendModule::utilization(BTC), Ratio::saturating_from_rational(9, 10));
+		assert!(model.annual_rate_at(LendModule::utilization(BTC)) > Ratio::saturating_from_rational(1, 20));
+	});
+}
+
+#[test]
+fn accrue_interest_uses_configured_interest_rate_model() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		// 800_000_000 debit_value out of 1_000_000_000 collateral_value (price 1.0): 80%
+		// utilization, at the jump. One block's accrual on this debit is about 60, large enough
+		// not to round down to zero.
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 1_000_000_000, 800_000_000));
+		assert_ok!(LendModule::set_interest_rate_model(
+			Origin::root(),
+			BTC,
+			InterestRateModel {
+				base_rate: Ratio::zero(),
+				jump_utilization: Ratio::saturating_from_rational(8, 10),
+				slope1: Ratio::saturating_from_rational(1, 2),
+				slope2: Ratio::one(),
+			}
+		));
+
+		// the flat `StabilityFee` stays zero, so any accrual must have come from the model.
+		LendModule::on_initialize(1);
+		assert!(LendModule::debit_exchange_rate(BTC).unwrap() > Ratio::one());
+		assert!(DEBTTreasuryModule::surplus_pool() > 0);
+	});
+}
+
+#[test]
+fn accrue_interest_compounds_debit_exchange_rate_and_credits_surplus() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		<StabilityFee<Runtime>>::put(Ratio::saturating_from_rational(1, 100));
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 1000, 100));
+		assert_eq!(LendModule::get_debit_value(BTC, 100), 100);
+
+		LendModule::on_initialize(1);
+		assert_eq!(
+			LendModule::debit_exchange_rate(BTC),
+			Some(Ratio::saturating_from_rational(101, 100))
+		);
+		assert_eq!(LendModule::get_debit_value(BTC, 100), 101);
+		assert_eq!(DEBTTreasuryModule::surplus_pool(), 1);
+
+		// the position's normalized debit is never rescaled; only the exchange rate used to value
+		// it moves.
+		assert_eq!(LendModule::total_positions(BTC).debit, 100);
+		assert_eq!(LendModule::positions(BTC, &ALICE).debit, 100);
+
+		// compounding again grows the rate further from where it left off, rather than resetting.
+		LendModule::on_initialize(2);
+		assert_eq!(
+			LendModule::debit_exchange_rate(BTC),
+			Some(Ratio::saturating_from_rational(10201, 10000))
+		);
+		assert_eq!(DEBTTreasuryModule::surplus_pool(), 2);
+	});
+}
+
+#[test]
+fn get_position_reports_values_and_health_factor() {
+	ExtBuilder::default().build().execute_with(|| {
+		// debt-free position: infinite health factor.
+		let (collateral, debit, collateral_value, debit_value, health_factor) = LendModule::get_position(BTC, ALICE);
+		assert_eq!((collateral, debit, collateral_value, debit_value), (0, 0, 0, 0));
+		assert_eq!(health_factor, Ratio::max_value());
+
+		// BTC's required collateral ratio is 3/2, so 600 collateral backing 300 debit
+		// (collateral_value 600, required_collateral_value 450) is safely above water.
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 600, 300));
+		let (collateral, debit, collateral_value, debit_value, health_factor) = LendModule::get_position(BTC, ALICE);
+		assert_eq!((collateral, debit, collateral_value, debit_value), (600, 300, 600, 300));
+		assert_eq!(health_factor, Ratio::saturating_from_rational(600, 450));
+	});
+}
+
+#[test]
+fn is_position_unsafe_flags_positions_below_required_ratio() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 600, 300));
+		assert_eq!(LendModule::is_position_unsafe(BTC, ALICE), false);
+
+		// collateral_value 500 against required_collateral_value 450 (300 * 3/2): still safe.
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, -100, 0));
+		assert_eq!(LendModule::is_position_unsafe(BTC, ALICE), false);
+
+		// collateral_value 400 against required_collateral_value 450: now unsafe.
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, -100, 0));
+		assert_eq!(LendModule::is_position_unsafe(BTC, ALICE), true);
+	});
+}
+
+#[test]
+fn close_loan_by_dex_swaps_collateral_to_repay_debit() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 600, 300));
+		assert_ok!(Currencies::deposit(BTC, &LendModule::account_id(), 600));
+
+		// the explicit 300 cap binds before the (much wider) slippage ceiling does, so MockDEX
+		// fills 1:1: repaying all 300 debit (debit_value 300) consumes exactly 300 collateral.
+		assert_ok!(LendModule::close_loan_by_dex(&ALICE, BTC, 300, 300));
+		assert_eq!(LendModule::positions(BTC, &ALICE).debit, 0);
+		assert_eq!(LendModule::positions(BTC, &ALICE).collateral, 300);
+		assert_eq!(Currencies::free_balance(BTC, &LendModule::account_id()), 300);
+
+		let closed_event = TestEvent::lend(RawEvent::ClosedLoanByDex(ALICE, BTC, 300, 300));
+		assert!(System::events().iter().any(|record| record.event == closed_event));
+	});
+}
+
+#[test]
+fn close_loan_by_dex_respects_max_collateral_amount() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 600, 300));
+		assert_ok!(Currencies::deposit(BTC, &LendModule::account_id(), 600));
+
+		// a cap below the 300 collateral the swap would need causes the DEX call to fail cleanly.
+		assert_eq!(LendModule::close_loan_by_dex(&ALICE, BTC, 300, 100).is_ok(), false);
+		assert_eq!(LendModule::positions(BTC, &ALICE).debit, 300);
+	});
+}
+
+#[test]
+fn close_loan_by_dex_rejects_swap_that_leaves_position_below_required_ratio() {
+	ExtBuilder::default().build().execute_with(|| {
+		// exactly at the 3/2 required collateral ratio: 1500 collateral backing 1000 debit value.
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 1500, 1000));
+		assert_ok!(Currencies::deposit(BTC, &LendModule::account_id(), 1500));
+
+		// `MockDEX` fills at the full slippage ceiling: repaying 400 debit value allows up to
+		// (1 + 3/5) * 400 = 640 collateral, more than 400 of debit relief is worth against a 3/2
+		// requirement. That leaves 1500 - 640 = 860 collateral backing the remaining 600 debit
+		// value, short of the 900 the ratio requires.
+		assert_eq!(
+			LendModule::close_loan_by_dex(&ALICE, BTC, 400, 640).is_ok(),
+			false
+		);
+		// the risk check runs after the swap and `update_loan`, but the whole call is rolled back
+		// on failure (as `adjust_position` is), so the position keeps its original values.
+		assert_eq!(LendModule::positions(BTC, &ALICE).debit, 1000);
+		assert_eq!(LendModule::positions(BTC, &ALICE).collateral, 1500);
+	});
+}
+
+#[test]
+fn get_liquidation_price_matches_required_collateral_ratio() {
+	ExtBuilder::default().build().execute_with(|| {
+		assert_eq!(LendModule::get_liquidation_price(BTC, ALICE), None);
+
+		// required_collateral_value is 300 * 3/2 = 450, spread over 600 collateral.
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 600, 300));
+		assert_eq!(
+			LendModule::get_liquidation_price(BTC, ALICE),
+			Some(Ratio::saturating_from_rational(450, 600))
+		);
+	});
+}
 
 #[test]
 fn debits_key() {
 	ExtBuilder::default().build().execute_with(|| {
 		assert_eq!(LendModule::positions(BTC, &ALICE).debit, 0);
-		assert_ok!(LendModule::adjust_position(&ALICE, BTC, 100, 100));
+		assert_ok!(LendModule::adjust_position(&ALICE, BTC, 200, 100));
 		assert_eq!(LendModule::positions(BTC, &ALICE).debit, 100);
-		assert_ok!(LendModule::adjust_position(&ALICE, BTC, -100, -100));
+		assert_ok!(LendModule::adjust_position(&ALICE, BTC, -200, -100));
 		assert_eq!(LendModule::positions(BTC, &ALICE).debit, 0);
 	});
 }
@@ -164,7 +440,82 @@ fn confiscate_collateral_and_debit_work() {
 		assert_eq!(LendModule::positions(BTC, &ALICE).debit, 100);
 		assert_eq!(LendModule::positions(BTC, &ALICE).collateral, 200);
 
-		let confiscate_event = TestEvent::lend(RawEvent::ConfiscateCollateralAndDebit(ALICE, BTC, 300, 200));
+		let confiscate_event = TestEvent::lend(RawEvent::ConfiscateCollateralAndDebit(ALICE, BTC, 300, 200, 0));
 		assert!(System::events().iter().any(|record| record.event == confiscate_event));
 	});
 }
+
+#[test]
+fn confiscate_with_liquidation_penalty_deposits_fee_and_system_debit() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(LendModule::set_liquidation_penalty(
+			Origin::root(),
+			BTC,
+			Ratio::saturating_from_rational(1, 10)
+		));
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 2000, 1000));
+		assert_ok!(Currencies::deposit(BTC, &LendModule::account_id(), 2000));
+
+		// bad debt of 1000 plus a 10% penalty raises the auction's target to 1100, minting 100
+		// XUSD into the fee account up front and registering the full 1100 as system debit.
+		assert_ok!(LendModule::confiscate_collateral_and_debit(&ALICE, BTC, 1500, 1000));
+		assert_eq!(Currencies::free_balance(XUSD, &FEE_ACCOUNT), 100);
+		assert_eq!(DEBTTreasuryModule::debit_pool(), 1100);
+
+		let auction = DEBTTreasuryModule::collateral_auctions(0).unwrap();
+		assert_eq!(auction.target, 1100);
+
+		let confiscate_event = TestEvent::lend(RawEvent::ConfiscateCollateralAndDebit(ALICE, BTC, 1500, 1000, 100));
+		assert!(System::events().iter().any(|record| record.event == confiscate_event));
+	});
+}
+
+#[test]
+fn confiscate_large_position_starts_collateral_auction() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 2000, 1000));
+		assert_ok!(Currencies::deposit(BTC, &LendModule::account_id(), 2000));
+
+		// lot of 1500 is above the mock's auction threshold of 1000, so it's sold through an
+		// auction rather than pooled directly.
+		assert_ok!(LendModule::confiscate_collateral_and_debit(&ALICE, BTC, 1500, 1000));
+		assert_eq!(DEBTTreasuryModule::get_total_collaterals(BTC), 0);
+		assert_eq!(DEBTTreasuryModule::debit_pool(), 1000);
+
+		let auction = DEBTTreasuryModule::collateral_auctions(0).unwrap();
+		assert_eq!(auction.amount, 1500);
+		assert_eq!(auction.target, 1000);
+		assert_eq!(auction.refund_recipient, ALICE);
+		assert_eq!(auction.start_price, Ratio::saturating_from_rational(21, 20));
+	});
+}
+
+#[test]
+fn collateral_auction_partial_bids_settle_and_pay_out() {
+	ExtBuilder::default().build().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(LendModule::update_loan(&ALICE, BTC, 2000, 1050));
+		assert_ok!(Currencies::deposit(BTC, &LendModule::account_id(), 2000));
+		assert_ok!(Currencies::deposit(XUSD, &BOB, 1050));
+		assert_ok!(LendModule::confiscate_collateral_and_debit(&ALICE, BTC, 1500, 1050));
+
+		// start_price is 1.05 (21/20); collateral is handed over strictly proportional to the
+		// payment, so paying 420 (a clean multiple of 21) buys exactly 420 * 20/21 = 400 BTC.
+		assert_ok!(DEBTTreasuryModule::bid_collateral_auction(Origin::signed(BOB), 0, 420));
+		assert_eq!(Currencies::free_balance(BTC, &BOB), 400);
+		assert_eq!(DEBTTreasuryModule::debit_pool(), 630);
+		let auction = DEBTTreasuryModule::collateral_auctions(0).unwrap();
+		assert_eq!(auction.amount, 1100);
+		assert_eq!(auction.target, 630);
+
+		// covering the rest (630 * 20/21 = 600 BTC) settles the auction and refunds the 500 BTC
+		// premium slack left in the lot back to the liquidated position's owner.
+		assert_ok!(DEBTTreasuryModule::bid_collateral_auction(Origin::signed(BOB), 0, 630));
+		assert_eq!(Currencies::free_balance(BTC, &BOB), 1000);
+		assert_eq!(DEBTTreasuryModule::debit_pool(), 0);
+		assert!(DEBTTreasuryModule::collateral_auctions(0).is_none());
+		assert_eq!(Currencies::free_balance(BTC, &ALICE), 1500);
+	});
+}